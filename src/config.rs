@@ -6,11 +6,9 @@
 // Author(s): Areg Baghinyan
 //
 
-use crate::utils::replace_env_vars;
-use anyhow::Result;
-use std::collections::HashMap;
-use hostname::get;
-use chrono::prelude::*;
+use crate::template::TemplateContext;
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::de::{self, Visitor};
 use std::fmt;
@@ -20,6 +18,10 @@ pub struct Config {
     // Tasks now use a HashMap to store dynamic sections (memory_tools, artifacts, etc.)
     pub tasks: HashMap<String, SectionConfig>,
     pub output_filename: String,
+    // Maximum number of entries allowed to run concurrently. Defaults to the
+    // detected CPU count when absent from the config.
+    #[serde(default)]
+    pub max_parallel: Option<usize>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -27,6 +29,11 @@ pub struct SectionConfig {
     pub priority: u8,
     pub r#type: TypeTasks,
     pub entries: HashMap<String, Vec<SearchConfig>>,
+    // Names of other task sections that must complete before this one starts.
+    // Takes precedence over `priority`, which only breaks ties among unrelated
+    // sections.
+    #[serde(default)]
+    pub depends_on: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone)]
@@ -128,11 +135,12 @@ impl<'de> Deserialize<'de> for TypeTasks {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TypeExec {
     External,
     Internal,
     System,
+    Script,
 }
 
 impl Serialize for TypeExec {
@@ -144,6 +152,7 @@ impl Serialize for TypeExec {
             TypeExec::External => serializer.serialize_str("external"),
             TypeExec::Internal => serializer.serialize_str("internal"),
             TypeExec::System => serializer.serialize_str("system"),
+            TypeExec::Script => serializer.serialize_str("script"),
         }
     }
 }
@@ -159,7 +168,7 @@ impl<'de> Deserialize<'de> for TypeExec {
             type Value = TypeExec;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a string containing 'external', 'internal' or 'system")
+                formatter.write_str("a string containing 'external', 'internal', 'system' or 'script'")
             }
 
             fn visit_str<E>(self, value: &str) -> Result<TypeExec, E>
@@ -170,7 +179,8 @@ impl<'de> Deserialize<'de> for TypeExec {
                     "external" => Ok(TypeExec::External),
                     "internal" => Ok(TypeExec::Internal),
                     "system" => Ok(TypeExec::System),
-                    _ => Err(de::Error::unknown_variant(value, &["external", "internal", "system"])),
+                    "script" => Ok(TypeExec::Script),
+                    _ => Err(de::Error::unknown_variant(value, &["external", "internal", "system", "script"])),
                 }
             }
         }
@@ -190,46 +200,152 @@ pub struct SearchConfig {
     pub encrypt: Option<String>,
     pub r#type: Option<TypeConfig>,
     pub exec_type: Option<TypeExec>,
+    // Embedded Lua script, used when `exec_type` is `script`. The script builds
+    // the command line at runtime and may register extra files for collection.
+    pub script: Option<String>,
+    // Optional schema describing how to normalize the tool's stdout into typed
+    // columns written beside the raw `output_file`.
+    pub parse: Option<crate::parse::ParseConfig>,
 }
 
 impl Config {
     pub fn load_from_embedded() -> Result<Self> {
         // Embed the YAML content directly into the binary
         let yaml_data = include_str!("config.yml");
-        let config: Config = serde_yaml::from_str(yaml_data)?;
+        let mut config: Config = serde_yaml::from_str(yaml_data)?;
+
+        // Expand every templated field up front so an unresolved variable is a
+        // hard error before any collection starts.
+        config.render_templates(&TemplateContext::new())?;
+
         Ok(config)
     }
 
-    pub fn get_output_filename(&self) -> String {
+    /// Render every templated string field in the config through `ctx`.
+    fn render_templates(&mut self, ctx: &TemplateContext) -> Result<()> {
+        self.output_filename = ctx.render(&self.output_filename)?;
 
-        let machine_name = get()
-            .ok()
-            .and_then(|hostname| hostname.into_string().ok())
-            .unwrap_or_else(|| "machine".to_string());
+        for section in self.tasks.values_mut() {
+            for entries in section.entries.values_mut() {
+                for entry in entries.iter_mut() {
+                    entry.render_templates(ctx)?;
+                }
+            }
+        }
 
-        let local: DateTime<Local> = Local::now();
-        let datetime = local.format("%Y-%m-%d_%H-%M-%S").to_string();
+        Ok(())
+    }
 
-        let mut vars: HashMap<&str, &str> = HashMap::new();
-        vars.insert("hostname", &machine_name);
-        vars.insert("datetime", &datetime);
+    pub fn get_output_filename(&self) -> String {
+        // Fields are rendered at load time, so the stored value is final.
+        self.output_filename.clone()
+    }
 
-        let mut output_filename_expand = self.output_filename.clone();
+    /// Number of entries allowed to run concurrently, falling back to the CPU
+    /// count (and at least one) when `max_parallel` is not set in the config.
+    pub fn max_parallel(&self) -> usize {
+        self.max_parallel.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+    }
 
-        for (key, value) in vars {
-            output_filename_expand = output_filename_expand.replace(&format!("{{{{{}}}}}", key), value);
-        }
-        output_filename_expand
+    /// Function to return tasks sections in dependency order.
+    ///
+    /// Sections are topologically sorted over their `depends_on` edges, with
+    /// `priority` used only to break ties among sections that have no dependency
+    /// relation. This is the flattened form of [`Config::get_task_barriers`].
+    pub fn get_tasks(&self) -> Result<Vec<(String, SectionConfig)>> {
+        Ok(self.get_task_barriers()?.into_iter().flatten().collect())
     }
 
-    /// Function to return tasks sections ordered by priority
-    pub fn get_tasks(&self) -> Vec<(String, SectionConfig)> {
-        let mut tasks_vec: Vec<(String, SectionConfig)> = self.tasks.clone().into_iter().collect();
+    /// Return task sections grouped into ordered dependency barriers.
+    ///
+    /// Each inner `Vec` is one barrier that may be scheduled concurrently; the
+    /// outer `Vec` is ordered so every section in barrier *n* finishes before any
+    /// section in barrier *n+1* starts. Two constraints shape the barriers, in
+    /// this order of precedence:
+    ///
+    /// 1. `depends_on`: a section never joins a barrier until every section it
+    ///    names has already been resolved by an earlier barrier.
+    /// 2. `priority`: among the sections whose dependencies are satisfied, only
+    ///    those at the lowest remaining priority form the next barrier. This
+    ///    keeps chunk0-1's guarantee that all priority-*n* work finishes before
+    ///    any priority-*(n+1)* work begins, with independent same-priority
+    ///    sections still running in parallel. A dependency can defer a
+    ///    low-priority-number section past a higher one it consumes.
+    ///
+    /// Cycles and references to unknown sections are reported as hard errors
+    /// before execution begins.
+    pub fn get_task_barriers(&self) -> Result<Vec<Vec<(String, SectionConfig)>>> {
+        // Deduplicated dependency edges per section. A name repeated in
+        // `depends_on` must be counted once, otherwise it could never be fully
+        // satisfied and a valid config would be reported as a cycle.
+        let mut deps: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for (name, section) in &self.tasks {
+            let mut set: HashSet<&str> = HashSet::new();
+            for dep in section.depends_on.iter().flatten() {
+                if !self.tasks.contains_key(dep) {
+                    return Err(anyhow!(
+                        "task `{}` depends on unknown section `{}`",
+                        name,
+                        dep
+                    ));
+                }
+                set.insert(dep.as_str());
+            }
+            deps.insert(name.as_str(), set);
+        }
+
+        let mut resolved: HashSet<&str> = HashSet::new();
+        let mut barriers: Vec<Vec<(String, SectionConfig)>> = Vec::new();
+
+        while resolved.len() < self.tasks.len() {
+            // Sections whose dependencies are all resolved.
+            let mut ready: Vec<&str> = self
+                .tasks
+                .keys()
+                .map(|k| k.as_str())
+                .filter(|name| {
+                    !resolved.contains(name)
+                        && deps[name].iter().all(|dep| resolved.contains(dep))
+                })
+                .collect();
+
+            if ready.is_empty() {
+                let remaining: Vec<&str> = self
+                    .tasks
+                    .keys()
+                    .map(|k| k.as_str())
+                    .filter(|k| !resolved.contains(k))
+                    .collect();
+                return Err(anyhow!(
+                    "dependency cycle detected among task sections: {:?}",
+                    remaining
+                ));
+            }
 
-        // Sort by priority
-        tasks_vec.sort_by_key(|(_, section)| section.priority);
+            // Keep only the lowest remaining priority so priority stays an
+            // ordered barrier; sort by name for a deterministic order.
+            let min_priority = ready
+                .iter()
+                .map(|name| self.tasks[*name].priority)
+                .min()
+                .expect("ready is non-empty");
+            ready.retain(|name| self.tasks[*name].priority == min_priority);
+            ready.sort();
+
+            let mut barrier: Vec<(String, SectionConfig)> = Vec::with_capacity(ready.len());
+            for name in &ready {
+                resolved.insert(name);
+                barrier.push((name.to_string(), self.tasks[*name].clone()));
+            }
+
+            barriers.push(barrier);
+        }
 
-        tasks_vec
+        Ok(barriers)
     }
 
     pub fn tasks_entries_len(&self) -> u64 {
@@ -246,9 +362,36 @@ impl Config {
 }
 
 impl SearchConfig {
-    // Method to get dir_path with environment variables replaced
+    /// Render every templated string field of this entry through `ctx`.
+    ///
+    /// `script` is deliberately excluded: a Lua body may legitimately contain
+    /// `{{` (e.g. a table literal) that the template engine would reject.
+    fn render_templates(&mut self, ctx: &TemplateContext) -> Result<()> {
+        for field in [
+            &mut self.dir_path,
+            &mut self.name,
+            &mut self.output_file,
+            &mut self.encrypt,
+        ] {
+            if let Some(value) = field {
+                *value = ctx.render(value)?;
+            }
+        }
+
+        for list in [&mut self.args, &mut self.objects] {
+            if let Some(values) = list {
+                for value in values.iter_mut() {
+                    *value = ctx.render(value)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // The dir_path is already template-rendered at load time.
     pub fn get_expanded_dir_path(&self) -> String {
-        replace_env_vars(&self.dir_path.clone().unwrap_or_default())
+        self.dir_path.clone().unwrap_or_default()
     }
 
     pub fn get_dir_path (&self) -> String {
@@ -258,3 +401,90 @@ impl SearchConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(priority: u8, depends_on: &[&str]) -> SectionConfig {
+        SectionConfig {
+            priority,
+            r#type: TypeTasks::Execute,
+            entries: HashMap::new(),
+            depends_on: if depends_on.is_empty() {
+                None
+            } else {
+                Some(depends_on.iter().map(|s| s.to_string()).collect())
+            },
+        }
+    }
+
+    fn config(tasks: &[(&str, SectionConfig)]) -> Config {
+        Config {
+            tasks: tasks
+                .iter()
+                .map(|(name, s)| (name.to_string(), s.clone()))
+                .collect(),
+            output_filename: "out".to_string(),
+            max_parallel: Some(4),
+        }
+    }
+
+    fn order(cfg: &Config) -> Vec<String> {
+        cfg.get_tasks()
+            .expect("valid config")
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    fn pos(order: &[String], name: &str) -> usize {
+        order.iter().position(|n| n == name).expect("name present")
+    }
+
+    #[test]
+    fn depends_on_orders_before_dependent() {
+        let cfg = config(&[("a", section(0, &["b"])), ("b", section(0, &[]))]);
+        let order = order(&cfg);
+        assert!(pos(&order, "b") < pos(&order, "a"));
+    }
+
+    #[test]
+    fn duplicate_dependency_is_not_a_cycle() {
+        // A repeated dependency must be counted once, not reported as a cycle.
+        let cfg = config(&[("a", section(0, &["b", "b"])), ("b", section(0, &[]))]);
+        let order = order(&cfg);
+        assert!(pos(&order, "b") < pos(&order, "a"));
+    }
+
+    #[test]
+    fn cycle_is_rejected() {
+        let cfg = config(&[("a", section(0, &["b"])), ("b", section(0, &["a"]))]);
+        assert!(cfg.get_task_barriers().is_err());
+    }
+
+    #[test]
+    fn unknown_dependency_is_rejected() {
+        let cfg = config(&[("a", section(0, &["ghost"]))]);
+        assert!(cfg.get_task_barriers().is_err());
+    }
+
+    #[test]
+    fn priority_is_an_ordered_barrier() {
+        // Two independent sections at different priorities land in separate
+        // barriers, lowest priority first.
+        let cfg = config(&[("late", section(5, &[])), ("early", section(1, &[]))]);
+        let barriers = cfg.get_task_barriers().expect("valid config");
+        assert_eq!(barriers.len(), 2);
+        assert_eq!(barriers[0][0].0, "early");
+        assert_eq!(barriers[1][0].0, "late");
+    }
+
+    #[test]
+    fn independent_same_priority_sections_share_a_barrier() {
+        let cfg = config(&[("a", section(0, &[])), ("b", section(0, &[]))]);
+        let barriers = cfg.get_task_barriers().expect("valid config");
+        assert_eq!(barriers.len(), 1);
+        assert_eq!(barriers[0].len(), 2);
+    }
+}
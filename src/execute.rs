@@ -10,16 +10,176 @@ mod network_info;
 mod process;
 mod process_details;
 
-use crate::config::ExecType;
+use crate::config::{Config, SearchConfig, TypeExec, TypeTasks};
+use crate::scheduler::{run_barrier, Token};
+use crate::store::{ContentStore, Provenance};
+use crate::parse::ParseConfig;
 
 use std::process::{Command, Stdio};
 use std::io::{self, Write};
 use std::fs::{File, remove_file};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use windows_sys::Win32::System::LibraryLoader::{GetModuleHandleA, FindResourceA, LoadResource, LockResource, SizeofResource};
 use std::ffi::CString;
 
-pub fn run_internal(tool_name:&str, output_filename: &str) {
+/// Schedule every task section through the jobserver pool.
+///
+/// Sections are resolved into ordered dependency barriers by
+/// [`Config::get_task_barriers`]; each barrier is run with [`run_barrier`] so
+/// its independent entries execute concurrently (bounded by `max_parallel`
+/// tokens) while the next barrier does not start until the current one drains.
+/// Every entry acquires a token for the lifetime of its `run`/`run_internal`
+/// call, capping the number of external binaries live at once.
+pub fn run_tasks(config: &Config) -> anyhow::Result<()> {
+    let max_parallel = config.max_parallel();
+    // One content-addressed store for the whole acquisition; its manifest is the
+    // run's provenance record.
+    let store = Arc::new(Mutex::new(ContentStore::new(config.get_output_filename())?));
+
+    for barrier in config.get_task_barriers()? {
+        let mut jobs: Vec<Box<dyn FnOnce(&Token) + Send>> = Vec::new();
+        for (_, section) in barrier {
+            let task_type = section.r#type.clone();
+            for entries in section.entries.into_values() {
+                for entry in entries {
+                    let store = Arc::clone(&store);
+                    let task_type = task_type.clone();
+                    jobs.push(Box::new(move |token: &Token| {
+                        run_entry(&entry, &task_type, token, &store)
+                    }));
+                }
+            }
+        }
+        run_barrier(jobs, max_parallel);
+    }
+
+    store.lock().unwrap().write_manifest()?;
+
+    Ok(())
+}
+
+/// Dispatch a single entry to the right executor while holding `token`.
+fn run_entry(
+    entry: &SearchConfig,
+    task_type: &TypeTasks,
+    token: &Token,
+    store: &Mutex<ContentStore>,
+) {
+    match task_type {
+        // Collect tasks gather files off disk; each is content-addressed and
+        // deduplicated through the store instead of being copied out verbatim.
+        TypeTasks::Collect => {
+            let objects = entry.objects.clone().unwrap_or_default();
+            collect_objects(&objects, entry.dir_path.as_deref(), store);
+        }
+        TypeTasks::Execute => run_execute(entry, token, store),
+    }
+}
+
+/// Content-address and store every file matching the entry's `objects`.
+///
+/// Each object is a path or glob, resolved relative to `dir_path` when set.
+/// Directories and non-matching patterns are skipped; failures are logged and
+/// never abort the rest of the collection.
+fn collect_objects(objects: &[String], dir_path: Option<&str>, store: &Mutex<ContentStore>) {
+    for object in objects {
+        let pattern = match dir_path {
+            Some(dir) => Path::new(dir).join(object).to_string_lossy().into_owned(),
+            None => object.clone(),
+        };
+
+        let paths = match glob::glob(&pattern) {
+            Ok(paths) => paths,
+            Err(e) => {
+                dprintln!("[ERROR] > collect | Invalid pattern `{}`: {}", pattern, e);
+                continue;
+            }
+        };
+
+        for path in paths.flatten() {
+            if !path.is_file() {
+                continue;
+            }
+            let source = path.to_string_lossy().into_owned();
+            if let Err(e) = store.lock().unwrap().store_file(&path, &source) {
+                dprintln!("[ERROR] > collect | Failed to store `{}`: {}", source, e);
+            }
+        }
+    }
+}
+
+/// Run an `execute` entry: spawn its tool (or build one from a script) while
+/// holding `token`.
+fn run_execute(entry: &SearchConfig, token: &Token, store: &Mutex<ContentStore>) {
+    let name = entry.name.clone().unwrap_or_default();
+    let output_file = entry.output_file.clone().unwrap_or_default();
+    let args: Vec<String> = entry.args.clone().unwrap_or_default();
+    let arg_refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+
+    match entry.exec_type {
+        Some(TypeExec::Internal) => run_internal(token, &name, &output_file),
+        Some(TypeExec::External) => {
+            let exe_bytes = match get_bin(name.clone()) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    dprintln!("[ERROR] > `{}` | {}", name, e);
+                    return;
+                }
+            };
+            run(
+                token,
+                name,
+                &arg_refs,
+                TypeExec::External,
+                Some(&exe_bytes),
+                entry.dir_path.as_deref(),
+                &output_file,
+                store,
+                entry.parse.as_ref(),
+            );
+        }
+        Some(TypeExec::Script) => {
+            let source = entry.script.clone().unwrap_or_default();
+            match crate::script::run_script(&source) {
+                Ok(plan) => {
+                    let plan_args: Vec<&str> = plan.args.iter().map(|s| s.as_str()).collect();
+                    run(
+                        token,
+                        plan.name,
+                        &plan_args,
+                        TypeExec::System,
+                        None,
+                        None,
+                        &output_file,
+                        store,
+                        entry.parse.as_ref(),
+                    );
+                    // Files the script registered via `cmd:collect(...)` are
+                    // acquired alongside the command's output.
+                    collect_objects(&plan.objects, entry.dir_path.as_deref(), store);
+                }
+                Err(e) => dprintln!("[ERROR] > `{}` | Failed to run script: {}", name, e),
+            }
+        }
+        _ => run(
+            token,
+            name,
+            &arg_refs,
+            TypeExec::System,
+            None,
+            None,
+            &output_file,
+            store,
+            entry.parse.as_ref(),
+        ),
+    }
+}
+
+pub fn run_internal(token: &Token, tool_name: &str, output_filename: &str) {
+    // Hold the jobserver token for the whole collection so no more than
+    // `max_parallel` internal collectors run at once.
+    let _token = token;
     dprintln!("[INFO] > `{}` | Starting execution", tool_name);
 
     // Create the full path for the output file
@@ -45,15 +205,22 @@ pub fn run_internal(tool_name:&str, output_filename: &str) {
 }
 
 pub fn run (
-    mut name: String, 
+    token: &Token,
+    mut name: String,
     args: &[&str],
-    exec_type: ExecType,
-    exe_bytes: Option<&[u8]>, 
-    output_path: Option<&str>, 
-    output_file: &str
+    exec_type: TypeExec,
+    exe_bytes: Option<&[u8]>,
+    output_path: Option<&str>,
+    output_file: &str,
+    store: &Mutex<ContentStore>,
+    parse: Option<&ParseConfig>
 ) {
+    // Hold the jobserver token across the spawn and the blocking
+    // `wait_with_output` below so the permit is only released once the tool has
+    // actually exited.
+    let _token = token;
     let mut display_name = name.clone();
-    if exec_type == ExecType::External {
+    if exec_type == TypeExec::External {
         // Save the executable to a temporary file
         let buffer = match exe_bytes {
             Some(bytes) => bytes,
@@ -112,14 +279,34 @@ pub fn run (
         }
     }; 
 
-    dprintln!("[INFO] > `{}` ({}) | Exit code: {:?}", display_name, pid, output.status.code().unwrap_or(-1));
+    let exit_code = output.status.code().unwrap_or(-1);
+    dprintln!("[INFO] > `{}` ({}) | Exit code: {:?}", display_name, pid, exit_code);
 
     // Save the result to the specified output path
     if let Err(e) = save_output_to_file(&output.stdout, output_file) {
         dprintln!("[ERROR] > `{}` ({}) | Failed to save output to file: {}", display_name, pid, e);
     }
 
-    if exec_type == ExecType::External {
+    // Record the captured stdout in the content-addressed store, deduplicating
+    // identical output and capturing the tool, args and exit code as provenance.
+    let provenance = Provenance {
+        tool: display_name.clone(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        exit_code,
+    };
+    if let Err(e) = store.lock().unwrap().store_bytes(&output.stdout, output_file, provenance) {
+        dprintln!("[ERROR] > `{}` ({}) | Failed to record output in store: {}", display_name, pid, e);
+    }
+
+    // When the entry carries a `parse` schema, normalize the raw stdout into a
+    // typed CSV/NDJSON beside the raw output.
+    if let Some(parse) = parse {
+        if let Err(e) = parse.normalize(&output.stdout, output_file) {
+            dprintln!("[ERROR] > `{}` ({}) | Failed to normalize output: {}", display_name, pid, e);
+        }
+    }
+
+    if exec_type == TypeExec::External {
         // Clean up the temporary file
         if let Err(e) = cleanup_temp_file(&name) {
             dprintln!("[ERROR] > `{}` ({}) | Failed to clean up temp file: {}", display_name, pid, e);
@@ -0,0 +1,371 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright © 2024 Areg Baghinyan. All Rights Reserved.
+//
+// Author(s): Areg Baghinyan
+//
+
+use anyhow::{anyhow, Result};
+use chrono::prelude::*;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+/// How a single column of delimited tool output is coerced into a typed value.
+#[derive(Debug, Clone)]
+pub enum Conversion {
+    /// Keep the raw bytes / string untouched.
+    Bytes,
+    String,
+    Integer,
+    Float,
+    Boolean,
+    /// An RFC3339 timestamp.
+    Timestamp,
+    /// A timestamp parsed with a strftime-style pattern, e.g. `%m/%d/%Y %H:%M:%S`.
+    TimestampFmt(String),
+}
+
+impl FromStr for Conversion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // A timestamp may carry its pattern after a `|`, e.g.
+        // `timestamp|%m/%d/%Y %H:%M:%S`.
+        let (name, fmt) = match s.split_once('|') {
+            Some((name, fmt)) => (name.trim(), Some(fmt.to_string())),
+            None => (s.trim(), None),
+        };
+
+        match name {
+            "bytes" => Ok(Conversion::Bytes),
+            "string" | "str" => Ok(Conversion::String),
+            "int" | "integer" => Ok(Conversion::Integer),
+            "float" => Ok(Conversion::Float),
+            "bool" | "boolean" => Ok(Conversion::Boolean),
+            "timestamp" => match fmt {
+                Some(fmt) => Ok(Conversion::TimestampFmt(fmt)),
+                None => Ok(Conversion::Timestamp),
+            },
+            other => Err(anyhow!("unknown conversion `{}`", other)),
+        }
+    }
+}
+
+impl Conversion {
+    /// Coerce one raw field, returning the typed value or an error describing
+    /// why the field is malformed.
+    fn apply(&self, raw: &str) -> Result<Value> {
+        match self {
+            Conversion::Bytes | Conversion::String => Ok(json!(raw)),
+            Conversion::Integer => Ok(json!(raw.trim().parse::<i64>()?)),
+            Conversion::Float => Ok(json!(raw.trim().parse::<f64>()?)),
+            Conversion::Boolean => Ok(json!(raw.trim().parse::<bool>()?)),
+            Conversion::Timestamp => {
+                let ts = DateTime::parse_from_rfc3339(raw.trim())?;
+                Ok(json!(ts.to_rfc3339()))
+            }
+            Conversion::TimestampFmt(fmt) => {
+                let ts = NaiveDateTime::parse_from_str(raw.trim(), fmt)?;
+                Ok(json!(ts.and_utc().to_rfc3339()))
+            }
+        }
+    }
+}
+
+impl Serialize for Conversion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let rendered = match self {
+            Conversion::Bytes => "bytes".to_string(),
+            Conversion::String => "string".to_string(),
+            Conversion::Integer => "int".to_string(),
+            Conversion::Float => "float".to_string(),
+            Conversion::Boolean => "bool".to_string(),
+            Conversion::Timestamp => "timestamp".to_string(),
+            Conversion::TimestampFmt(fmt) => format!("timestamp|{}", fmt),
+        };
+        serializer.serialize_str(&rendered)
+    }
+}
+
+impl<'de> Deserialize<'de> for Conversion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ConversionVisitor;
+
+        impl<'de> Visitor<'de> for ConversionVisitor {
+            type Value = Conversion;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a conversion name such as 'int', 'float', 'bool' or 'timestamp|%m/%d/%Y'")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Conversion, E>
+            where
+                E: de::Error,
+            {
+                Conversion::from_str(value).map_err(|e| de::Error::custom(e.to_string()))
+            }
+        }
+
+        deserializer.deserialize_str(ConversionVisitor)
+    }
+}
+
+/// Serialized form of a parsed table: one per normalized output file.
+#[derive(Debug, Clone)]
+pub enum OutputFormat {
+    Csv,
+    Ndjson,
+}
+
+impl Serialize for OutputFormat {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            OutputFormat::Csv => serializer.serialize_str("csv"),
+            OutputFormat::Ndjson => serializer.serialize_str("ndjson"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OutputFormat {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct OutputFormatVisitor;
+
+        impl<'de> Visitor<'de> for OutputFormatVisitor {
+            type Value = OutputFormat;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string containing 'csv' or 'ndjson'")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<OutputFormat, E>
+            where
+                E: de::Error,
+            {
+                match value {
+                    "csv" => Ok(OutputFormat::Csv),
+                    "ndjson" => Ok(OutputFormat::Ndjson),
+                    _ => Err(de::Error::unknown_variant(value, &["csv", "ndjson"])),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(OutputFormatVisitor)
+    }
+}
+
+/// One `(column_name, Conversion)` pair of the parse schema.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ColumnSpec {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub convert: Conversion,
+}
+
+/// Optional `parse` block describing how to normalize a tool's stdout.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ParseConfig {
+    pub delimiter: String,
+    pub columns: Vec<ColumnSpec>,
+    #[serde(default = "default_format")]
+    pub format: OutputFormat,
+}
+
+fn default_format() -> OutputFormat {
+    OutputFormat::Csv
+}
+
+impl ParseConfig {
+    /// Turn raw tool stdout into a normalized file beside `raw_output`.
+    ///
+    /// Each line is split on the delimiter and the columns are coerced in order;
+    /// rows whose field count mismatches or whose conversions fail are dropped
+    /// and logged rather than aborting the whole parse.
+    pub fn normalize(&self, stdout: &[u8], raw_output: &str) -> Result<()> {
+        let text = String::from_utf8_lossy(stdout);
+        let mut rows: Vec<Vec<Value>> = Vec::new();
+
+        for (lineno, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(&self.delimiter).collect();
+            if fields.len() != self.columns.len() {
+                dprintln!(
+                    "[WARN] > parse | Dropping line {}: expected {} columns, got {}",
+                    lineno + 1,
+                    self.columns.len(),
+                    fields.len()
+                );
+                continue;
+            }
+
+            match self.convert_row(&fields) {
+                Ok(row) => rows.push(row),
+                Err(e) => dprintln!("[WARN] > parse | Dropping line {}: {}", lineno + 1, e),
+            }
+        }
+
+        let out_path = self.output_path(raw_output);
+        self.write(&rows, &out_path)
+    }
+
+    fn convert_row(&self, fields: &[&str]) -> Result<Vec<Value>> {
+        self.columns
+            .iter()
+            .zip(fields)
+            .map(|(column, raw)| column.convert.apply(raw))
+            .collect()
+    }
+
+    /// Normalized output path: the raw file name with a `.csv`/`.ndjson`
+    /// extension appended, so the raw stdout is never overwritten even when it
+    /// already carries that extension (e.g. `foo.csv` -> `foo.csv.csv`).
+    fn output_path(&self, raw_output: &str) -> std::path::PathBuf {
+        let ext = match self.format {
+            OutputFormat::Csv => "csv",
+            OutputFormat::Ndjson => "ndjson",
+        };
+        std::path::PathBuf::from(format!("{}.{}", raw_output, ext))
+    }
+
+    fn write(&self, rows: &[Vec<Value>], out_path: &Path) -> Result<()> {
+        let mut file = File::create(out_path)?;
+        match self.format {
+            OutputFormat::Csv => {
+                let header: Vec<&str> = self.columns.iter().map(|c| c.name.as_str()).collect();
+                writeln!(file, "{}", header.join(","))?;
+                for row in rows {
+                    let cells: Vec<String> = row.iter().map(render_csv_cell).collect();
+                    writeln!(file, "{}", cells.join(","))?;
+                }
+            }
+            OutputFormat::Ndjson => {
+                for row in rows {
+                    let obj: serde_json::Map<String, Value> = self
+                        .columns
+                        .iter()
+                        .map(|c| c.name.clone())
+                        .zip(row.iter().cloned())
+                        .collect();
+                    writeln!(file, "{}", serde_json::to_string(&obj)?)?;
+                }
+            }
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Render one typed cell for CSV, quoting strings that contain a comma or quote.
+fn render_csv_cell(value: &Value) -> String {
+    match value {
+        Value::String(s) => {
+            if s.contains(',') || s.contains('"') || s.contains('\n') {
+                format!("\"{}\"", s.replace('"', "\"\""))
+            } else {
+                s.clone()
+            }
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_names_parse() {
+        assert!(matches!("int".parse::<Conversion>().unwrap(), Conversion::Integer));
+        assert!(matches!("bool".parse::<Conversion>().unwrap(), Conversion::Boolean));
+        assert!(matches!(
+            "timestamp|%m/%d/%Y".parse::<Conversion>().unwrap(),
+            Conversion::TimestampFmt(fmt) if fmt == "%m/%d/%Y"
+        ));
+        assert!("nonsense".parse::<Conversion>().is_err());
+    }
+
+    #[test]
+    fn conversions_coerce_values() {
+        assert_eq!(Conversion::Integer.apply(" 42 ").unwrap(), json!(42));
+        assert_eq!(Conversion::Float.apply("3.5").unwrap(), json!(3.5));
+        assert_eq!(Conversion::Boolean.apply("true").unwrap(), json!(true));
+        assert!(Conversion::Integer.apply("not-a-number").is_err());
+    }
+
+    #[test]
+    fn timestamp_fmt_is_normalized_to_rfc3339() {
+        let converted = Conversion::TimestampFmt("%m/%d/%Y %H:%M:%S".to_string())
+            .apply("01/02/2024 03:04:05")
+            .unwrap();
+        assert_eq!(converted, json!("2024-01-02T03:04:05+00:00"));
+    }
+
+    #[test]
+    fn malformed_rows_are_dropped() {
+        let parse = ParseConfig {
+            delimiter: ",".to_string(),
+            columns: vec![
+                ColumnSpec { name: "name".to_string(), convert: Conversion::String },
+                ColumnSpec { name: "pid".to_string(), convert: Conversion::Integer },
+            ],
+            format: OutputFormat::Csv,
+        };
+        let raw = std::env::temp_dir().join("aralez_parse_malformed.raw");
+        let stdout = b"alpha,1\nbad-row\nbravo,2\ncharlie,notint\n";
+        parse.normalize(stdout, raw.to_str().unwrap()).unwrap();
+
+        let csv = std::fs::read_to_string(parse.output_path(raw.to_str().unwrap())).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        // Header plus the two well-formed rows only.
+        assert_eq!(lines, vec!["name,pid", "alpha,1", "bravo,2"]);
+    }
+
+    #[test]
+    fn strings_with_commas_are_quoted() {
+        assert_eq!(render_csv_cell(&json!("a,b")), "\"a,b\"");
+        assert_eq!(render_csv_cell(&json!("he said \"hi\"")), "\"he said \"\"hi\"\"\"");
+        assert_eq!(render_csv_cell(&json!("plain")), "plain");
+        assert_eq!(render_csv_cell(&json!(7)), "7");
+    }
+
+    #[test]
+    fn output_path_uses_format_extension() {
+        let parse = ParseConfig {
+            delimiter: "\t".to_string(),
+            columns: vec![],
+            format: OutputFormat::Ndjson,
+        };
+        assert_eq!(parse.output_path("dir/out.raw"), Path::new("dir/out.raw.ndjson"));
+    }
+
+    #[test]
+    fn normalized_path_never_clobbers_a_csv_raw_output() {
+        let parse = ParseConfig {
+            delimiter: ",".to_string(),
+            columns: vec![],
+            format: OutputFormat::Csv,
+        };
+        assert_ne!(parse.output_path("out.csv"), Path::new("out.csv"));
+        assert_eq!(parse.output_path("out.csv"), Path::new("out.csv.csv"));
+    }
+}
@@ -0,0 +1,84 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright © 2024 Areg Baghinyan. All Rights Reserved.
+//
+// Author(s): Areg Baghinyan
+//
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+/// A jobserver-style pool of concurrency tokens.
+///
+/// `N` permits are created up front; every unit of work must acquire a token
+/// before launching its `Command` (or internal collector) and releases it on
+/// completion. Acquisition blocks while the pool is empty, capping the number
+/// of external binaries running at once so a collection run that spawns many
+/// Sysinternals tools does not overwhelm the host.
+#[derive(Clone)]
+pub struct TokenPool {
+    inner: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl TokenPool {
+    /// Create a pool holding `permits` tokens.
+    pub fn new(permits: usize) -> Self {
+        // Always keep at least one token so the scheduler makes progress.
+        let permits = permits.max(1);
+        TokenPool {
+            inner: Arc::new((Mutex::new(permits), Condvar::new())),
+        }
+    }
+
+    /// Block until a token is available, returning a guard that puts the token
+    /// back into the pool when dropped.
+    pub fn acquire(&self) -> Token {
+        let (lock, cvar) = &*self.inner;
+        let mut available = lock.lock().unwrap();
+        while *available == 0 {
+            available = cvar.wait(available).unwrap();
+        }
+        *available -= 1;
+        Token {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+/// RAII handle returned by [`TokenPool::acquire`]; releases its token on drop.
+pub struct Token {
+    inner: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.inner;
+        let mut available = lock.lock().unwrap();
+        *available += 1;
+        cvar.notify_one();
+    }
+}
+
+/// Run every job in `jobs` concurrently, bounded by `max_parallel` tokens, and
+/// wait for all of them to finish before returning.
+///
+/// This is the body of a single priority barrier: all entries handed in here
+/// belong to the same `priority` and may run in any order, but the caller must
+/// not start the next barrier until this call returns.
+pub fn run_barrier<F>(jobs: Vec<F>, max_parallel: usize)
+where
+    F: FnOnce(&Token) + Send,
+{
+    let pool = TokenPool::new(max_parallel);
+
+    thread::scope(|scope| {
+        for job in jobs {
+            let pool = pool.clone();
+            scope.spawn(move || {
+                let token = pool.acquire();
+                job(&token);
+            });
+        }
+    });
+}
@@ -0,0 +1,93 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright © 2024 Areg Baghinyan. All Rights Reserved.
+//
+// Author(s): Areg Baghinyan
+//
+
+use anyhow::Result;
+use mlua::{Lua, UserData, UserDataMethods};
+use std::env;
+use std::sync::{Arc, Mutex};
+use sysinfo::System;
+
+use hostname::get;
+
+/// Result of running a `script` task: the command line the script assembled
+/// plus any additional files/globs it registered for collection.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptPlan {
+    pub name: String,
+    pub args: Vec<String>,
+    pub objects: Vec<String>,
+}
+
+/// The command-line builder exposed to Lua as `cmd`.
+///
+/// Scripts mutate it through `cmd:set_name(..)`, `cmd:arg(..)` and
+/// `cmd:collect(..)`; the accumulated state is read back into a [`ScriptPlan`]
+/// once the script returns.
+#[derive(Clone)]
+struct CmdBuilder {
+    inner: Arc<Mutex<ScriptPlan>>,
+}
+
+impl UserData for CmdBuilder {
+    fn add_methods<M: UserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("set_name", |_, this, name: String| {
+            this.inner.lock().unwrap().name = name;
+            Ok(())
+        });
+        methods.add_method("arg", |_, this, arg: String| {
+            this.inner.lock().unwrap().args.push(arg);
+            Ok(())
+        });
+        methods.add_method("collect", |_, this, object: String| {
+            this.inner.lock().unwrap().objects.push(object);
+            Ok(())
+        });
+    }
+}
+
+/// Read-only host facts exposed to the script as the `host` table.
+fn build_host_facts(lua: &Lua) -> Result<mlua::Table> {
+    let host = lua.create_table()?;
+
+    let machine_name = get()
+        .ok()
+        .and_then(|hostname| hostname.into_string().ok())
+        .unwrap_or_else(|| "machine".to_string());
+    host.set("hostname", machine_name)?;
+    // `env::consts::OS` is the OS *name* ("windows"/"linux"); expose the actual
+    // OS version string, falling back to the name when it cannot be read.
+    let os_version = System::os_version().unwrap_or_else(|| env::consts::OS.to_string());
+    host.set("os_version", os_version)?;
+    host.set("arch", env::consts::ARCH)?;
+
+    let env_table = lua.create_table()?;
+    for (key, value) in env::vars() {
+        env_table.set(key, value)?;
+    }
+    host.set("env", env_table)?;
+
+    Ok(host)
+}
+
+/// Load `source` in an embedded Lua interpreter, expose the host API, run it and
+/// return the command line and collection set it produced.
+pub fn run_script(source: &str) -> Result<ScriptPlan> {
+    let lua = Lua::new();
+
+    let builder = CmdBuilder {
+        inner: Arc::new(Mutex::new(ScriptPlan::default())),
+    };
+
+    lua.globals().set("host", build_host_facts(&lua)?)?;
+    lua.globals().set("cmd", builder.clone())?;
+
+    lua.load(source).exec()?;
+
+    let plan = builder.inner.lock().unwrap().clone();
+    Ok(plan)
+}
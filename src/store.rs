@@ -0,0 +1,146 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright © 2024 Areg Baghinyan. All Rights Reserved.
+//
+// Author(s): Areg Baghinyan
+//
+
+use anyhow::Result;
+use chrono::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+// Read/hash files in 1 MiB chunks so large `max_size` artifacts and memory
+// images from winpmem are never buffered whole in RAM.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Provenance recorded for an `execute` task whose stdout is stored.
+#[derive(Debug, Clone, Serialize)]
+pub struct Provenance {
+    pub tool: String,
+    pub args: Vec<String>,
+    pub exit_code: i32,
+}
+
+/// One logical source path resolved to the content object it maps to.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestEntry {
+    pub source: String,
+    pub hash: String,
+    pub size: u64,
+    pub collected_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// Content-addressed object store kept for the lifetime of one acquisition.
+///
+/// Unique objects live under `objects/<first-2-hex>/<full-hash>`; identical
+/// bytes seen again are recorded as another manifest entry pointing at the same
+/// object instead of being rewritten. The manifest doubles as a tamper-evidence
+/// and provenance record for the whole run.
+pub struct ContentStore {
+    root: PathBuf,
+    seen: HashMap<String, PathBuf>,
+    manifest: Vec<ManifestEntry>,
+}
+
+impl ContentStore {
+    /// Create a store rooted at `root`, creating the `objects/` directory.
+    pub fn new<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        fs::create_dir_all(root.join("objects"))?;
+        Ok(ContentStore {
+            root,
+            seen: HashMap::new(),
+            manifest: Vec::new(),
+        })
+    }
+
+    /// Stream an existing file into the store, deduplicating by content hash.
+    pub fn store_file(&mut self, path: &Path, source: &str) -> Result<String> {
+        let mut reader = File::open(path)?;
+        self.ingest(&mut reader, source, None)
+    }
+
+    /// Store in-memory bytes (e.g. a tool's captured stdout) with provenance.
+    pub fn store_bytes(
+        &mut self,
+        bytes: &[u8],
+        source: &str,
+        provenance: Provenance,
+    ) -> Result<String> {
+        let mut reader = bytes;
+        self.ingest(&mut reader, source, Some(provenance))
+    }
+
+    /// Hash `reader` in chunks while writing the unique object, then record a
+    /// manifest entry for `source`.
+    fn ingest<R: Read>(
+        &mut self,
+        reader: &mut R,
+        source: &str,
+        provenance: Option<Provenance>,
+    ) -> Result<String> {
+        let mut hasher = blake3::Hasher::new();
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut size: u64 = 0;
+
+        // Write to a temporary object first; only once we know the final hash do
+        // we know where the object belongs (and whether it already exists).
+        let tmp_path = self.root.join("objects").join(".incoming");
+        let mut tmp = File::create(&tmp_path)?;
+
+        loop {
+            let read = reader.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buf[..read]);
+            tmp.write_all(&buf[..read])?;
+            size += read as u64;
+        }
+        tmp.flush()?;
+
+        let hash = hasher.finalize().to_hex().to_string();
+        let object_path = self.object_path(&hash);
+
+        if self.seen.contains_key(&hash) {
+            // Duplicate content: drop the temp copy, keep only the reference.
+            fs::remove_file(&tmp_path)?;
+        } else {
+            fs::create_dir_all(object_path.parent().expect("object path has a parent"))?;
+            fs::rename(&tmp_path, &object_path)?;
+            self.seen.insert(hash.clone(), object_path);
+        }
+
+        self.manifest.push(ManifestEntry {
+            source: source.to_string(),
+            hash: hash.clone(),
+            size,
+            collected_at: Local::now().to_rfc3339(),
+            provenance,
+        });
+
+        Ok(hash)
+    }
+
+    /// Physical location of the object for `hash`: `objects/<2-hex>/<hash>`.
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.root.join("objects").join(&hash[..2]).join(hash)
+    }
+
+    /// Serialize the manifest to `manifest.json` at the store root.
+    pub fn write_manifest(&self) -> Result<()> {
+        let manifest_path = self.root.join("manifest.json");
+        let json = serde_json::to_string_pretty(&self.manifest)?;
+        let mut file = File::create(manifest_path)?;
+        file.write_all(json.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+// Copyright © 2024 Areg Baghinyan. All Rights Reserved.
+//
+// Author(s): Areg Baghinyan
+//
+
+use anyhow::{anyhow, Result};
+use chrono::prelude::*;
+use std::collections::HashMap;
+use std::env;
+use sysinfo::System;
+
+use hostname::get;
+
+/// Rendering context shared by every templated field in the config.
+///
+/// It is built once per run so that `{{datetime}}`, `{{epoch}}` and friends are
+/// consistent across all output paths, and exposes host facts plus the full
+/// environment map. A single context means a `{{var}}` that does not resolve can
+/// be reported before collection starts, instead of silently expanding to the
+/// literal braces.
+pub struct TemplateContext {
+    vars: HashMap<String, String>,
+    now: DateTime<Local>,
+}
+
+impl TemplateContext {
+    /// Collect host facts, the current time and the environment into a context.
+    pub fn new() -> Self {
+        let now = Local::now();
+        let mut vars: HashMap<String, String> = HashMap::new();
+
+        let hostname = get()
+            .ok()
+            .and_then(|hostname| hostname.into_string().ok())
+            .unwrap_or_else(|| "machine".to_string());
+        vars.insert("hostname".to_string(), hostname);
+
+        vars.insert("datetime".to_string(), now.format("%Y-%m-%d_%H-%M-%S").to_string());
+        vars.insert("date".to_string(), now.format("%Y-%m-%d").to_string());
+        vars.insert("time".to_string(), now.format("%H-%M-%S").to_string());
+        vars.insert("epoch".to_string(), now.timestamp().to_string());
+
+        // `env::consts::OS` is the OS *name* ("windows"/"linux"); expose the
+        // actual OS version, falling back to the name when it cannot be read, so
+        // this agrees with the `host.os_version` fact in `script.rs`.
+        let os_version = System::os_version().unwrap_or_else(|| env::consts::OS.to_string());
+        vars.insert("os_version".to_string(), os_version);
+        vars.insert("arch".to_string(), env::consts::ARCH.to_string());
+
+        // `USERNAME`/`USERDOMAIN` are the Windows spellings; fall back to the
+        // POSIX `USER` so the context is still populated off-target.
+        let username = env::var("USERNAME")
+            .or_else(|_| env::var("USER"))
+            .unwrap_or_default();
+        vars.insert("username".to_string(), username);
+        vars.insert("domain".to_string(), env::var("USERDOMAIN").unwrap_or_default());
+
+        for (key, value) in env::vars() {
+            vars.insert(format!("env.{}", key), value);
+        }
+
+        TemplateContext { vars, now }
+    }
+
+    /// Render one template string, expanding `{{var}}` expressions with optional
+    /// `| lower` / `| upper` filters and a `var:"%Y%m%d"` date reformatter.
+    ///
+    /// For backwards compatibility with configs written against the old
+    /// `replace_env_vars` pass, `%VAR%`, `${VAR}` and `$VAR` environment syntax
+    /// is still expanded afterwards; an unset variable is left untouched.
+    ///
+    /// Returns an error naming the first unresolved `{{var}}` so a bad config is
+    /// caught up front.
+    pub fn render(&self, template: &str) -> Result<String> {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let end = after
+                .find("}}")
+                .ok_or_else(|| anyhow!("unterminated `{{{{` in template `{}`", template))?;
+            let expr = after[..end].trim();
+            out.push_str(&self.eval(expr)?);
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+
+        Ok(expand_env_syntax(&out))
+    }
+
+    /// Evaluate a single `{{...}}` expression.
+    fn eval(&self, expr: &str) -> Result<String> {
+        let mut parts = expr.split('|');
+        let head = parts.next().unwrap_or("").trim();
+
+        // The head is either a bare variable or a `name:"fmt"` date reformatter.
+        let mut value = if let Some((name, fmt)) = parse_date_spec(head) {
+            // Reformat the run timestamp regardless of which time variable the
+            // analyst named; all of them derive from the same instant.
+            if !matches!(name.as_str(), "datetime" | "date" | "time" | "epoch") {
+                return Err(anyhow!("unknown template variable `{}`", name));
+            }
+            self.now.format(&fmt).to_string()
+        } else {
+            self.vars
+                .get(head)
+                .cloned()
+                .ok_or_else(|| anyhow!("unknown template variable `{}`", head))?
+        };
+
+        for filter in parts {
+            value = apply_filter(filter.trim(), value)?;
+        }
+
+        Ok(value)
+    }
+}
+
+impl Default for TemplateContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Expand `%VAR%`, `${VAR}` and `$VAR` environment references in `input`.
+///
+/// This preserves the behaviour of the old `replace_env_vars` pass so existing
+/// configs keep working alongside the `{{env.X}}` form. A reference to an unset
+/// variable is left verbatim rather than collapsing to an empty string.
+fn expand_env_syntax(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => {
+                // `%VAR%`, Windows style.
+                let mut name = String::new();
+                let mut closed = false;
+                while let Some(&next) = chars.peek() {
+                    if next == '%' {
+                        chars.next();
+                        closed = true;
+                        break;
+                    }
+                    name.push(next);
+                    chars.next();
+                }
+                match (closed, env::var(&name)) {
+                    (true, Ok(value)) => out.push_str(&value),
+                    (true, Err(_)) => {
+                        out.push('%');
+                        out.push_str(&name);
+                        out.push('%');
+                    }
+                    (false, _) => {
+                        // No closing `%`; emit the literal text unchanged.
+                        out.push('%');
+                        out.push_str(&name);
+                    }
+                }
+            }
+            '$' => {
+                // `${VAR}` or `$VAR`, POSIX style.
+                let braced = chars.peek() == Some(&'{');
+                if braced {
+                    chars.next();
+                }
+                let mut name = String::new();
+                while let Some(&next) = chars.peek() {
+                    let is_name_char = next.is_ascii_alphanumeric() || next == '_';
+                    if braced {
+                        if next == '}' {
+                            chars.next();
+                            break;
+                        }
+                        name.push(next);
+                        chars.next();
+                    } else if is_name_char {
+                        name.push(next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                match env::var(&name) {
+                    Ok(value) => out.push_str(&value),
+                    Err(_) => {
+                        out.push('$');
+                        if braced {
+                            out.push('{');
+                            out.push_str(&name);
+                            out.push('}');
+                        } else {
+                            out.push_str(&name);
+                        }
+                    }
+                }
+            }
+            other => out.push(other),
+        }
+    }
+
+    out
+}
+
+/// Parse a `name:"fmt"` head into its variable name and strftime pattern.
+fn parse_date_spec(head: &str) -> Option<(String, String)> {
+    let (name, rest) = head.split_once(':')?;
+    let fmt = rest.trim();
+    let fmt = fmt.strip_prefix('"')?.strip_suffix('"')?;
+    Some((name.trim().to_string(), fmt.to_string()))
+}
+
+/// Apply a pipe filter (`lower`/`upper`) to an already-resolved value.
+fn apply_filter(filter: &str, value: String) -> Result<String> {
+    match filter {
+        "lower" => Ok(value.to_lowercase()),
+        "upper" => Ok(value.to_uppercase()),
+        other => Err(anyhow!("unknown template filter `{}`", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_change_case() {
+        std::env::set_var("ARALEZ_TMPL_CASE", "MixedCase");
+        let ctx = TemplateContext::new();
+        assert_eq!(ctx.render("{{env.ARALEZ_TMPL_CASE | lower}}").unwrap(), "mixedcase");
+        assert_eq!(ctx.render("{{env.ARALEZ_TMPL_CASE | upper}}").unwrap(), "MIXEDCASE");
+    }
+
+    #[test]
+    fn date_reformatter_matches_date_var() {
+        let ctx = TemplateContext::new();
+        // Both derive from the same run instant, so reformatting to the `date`
+        // pattern reproduces the `date` variable exactly.
+        assert_eq!(
+            ctx.render("{{date:\"%Y-%m-%d\"}}").unwrap(),
+            ctx.render("{{date}}").unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_variable_is_an_error() {
+        let ctx = TemplateContext::new();
+        assert!(ctx.render("{{definitely_not_a_var}}").is_err());
+    }
+
+    #[test]
+    fn unknown_filter_is_an_error() {
+        let ctx = TemplateContext::new();
+        assert!(ctx.render("{{hostname | sideways}}").is_err());
+    }
+
+    #[test]
+    fn env_syntax_is_expanded() {
+        std::env::set_var("ARALEZ_TMPL_ENV", "value");
+        let ctx = TemplateContext::new();
+        assert_eq!(ctx.render("%ARALEZ_TMPL_ENV%").unwrap(), "value");
+        assert_eq!(ctx.render("${ARALEZ_TMPL_ENV}").unwrap(), "value");
+        assert_eq!(ctx.render("$ARALEZ_TMPL_ENV/x").unwrap(), "value/x");
+    }
+
+    #[test]
+    fn unset_env_reference_is_left_intact() {
+        let ctx = TemplateContext::new();
+        assert_eq!(ctx.render("%ARALEZ_UNSET_VAR%").unwrap(), "%ARALEZ_UNSET_VAR%");
+    }
+}